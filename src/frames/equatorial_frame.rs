@@ -0,0 +1,297 @@
+/*
+ * ANISE Toolkit
+ * Copyright (C) 2021-2022 Christopher Rabotin <christopher.rabotin@gmail.com> et al. (cf. AUTHORS.md)
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ *
+ * Documentation: https://nyxspace.com/
+ */
+use core::fmt;
+use hifitime::{Epoch, Unit};
+use nalgebra::Vector3;
+
+/// One astronomical unit, in kilometers, used to scale the observer's barycentric position when
+/// computing the parallactic displacement.
+const AU_KM: f64 = 149_597_870.7;
+
+/// Number of Julian days per Julian century, used to convert epoch differences into the `T`
+/// argument of the IAU 1976 precession polynomials.
+const DAYS_PER_JULIAN_CENTURY: f64 = 36525.0;
+
+/// Julian date (UTC) of the J2000.0 reference epoch, used to compute `T0`, the number of Julian
+/// centuries between J2000.0 and a catalog epoch that is not itself J2000.0 (e.g. B1950).
+const J2000_JDE: f64 = 2_451_545.0;
+
+/// An `EquatorialFrame` describes a cataloged inertial target by right ascension and declination
+/// at a reference equinox/epoch, with optional proper motion, annual parallax, and radial
+/// velocity, mirroring the data model of `Astro::Coords::Equatorial`.
+///
+/// Unlike [`super::geodetic_frame::GeodeticFrame`], which is body-fixed, this frame describes a
+/// single inertial direction (e.g. a star or a quasar) rather than a surface.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EquatorialFrame {
+    /// Catalog right ascension, in degrees, at `catalog_epoch`.
+    pub right_ascension_deg: f64,
+    /// Catalog declination, in degrees, at `catalog_epoch`.
+    pub declination_deg: f64,
+    /// Catalog equinox/epoch of the coordinates above (e.g. B1950 or J2000).
+    pub catalog_epoch: Epoch,
+    /// Proper motion in right ascension, in degrees per year.
+    pub proper_motion_ra_deg_year: f64,
+    /// Proper motion in declination, in degrees per year.
+    pub proper_motion_dec_deg_year: f64,
+    /// Annual parallax, in arcseconds.
+    pub parallax_arcsec: f64,
+    /// Radial velocity, in kilometers per second (positive receding).
+    pub radial_velocity_km_s: f64,
+}
+
+impl EquatorialFrame {
+    /// Returns the unit pointing vector of the catalog position, with no propagation applied.
+    pub fn unit_vector(&self) -> Vector3<f64> {
+        radec_to_unit_vector(self.right_ascension_deg, self.declination_deg)
+    }
+
+    /// Propagates the apparent right ascension/declination, in degrees, to `epoch`, given the
+    /// observer's barycentric position `observer_barycentric_km` (e.g. the Earth's position
+    /// relative to the solar system barycenter) expressed in the same inertial frame as the
+    /// catalog coordinates.
+    ///
+    /// The propagation applies, in order:
+    /// 1. linear proper motion from `catalog_epoch` to `epoch`;
+    /// 2. the parallactic displacement due to the observer's offset from the catalog's
+    ///    reference point (assumed barycentric);
+    /// 3. precession of the mean equator/equinox from `catalog_epoch` to `epoch`, via the
+    ///    standard IAU 1976 precession angles (ζ, z, θ) about the z, z, and y axes.
+    pub fn propagate_to(&self, epoch: Epoch, observer_barycentric_km: Vector3<f64>) -> (f64, f64) {
+        let days = (epoch - self.catalog_epoch).to_seconds() / Unit::Day.in_seconds();
+        let years = days / 365.25;
+
+        let ra_pm_deg = self.right_ascension_deg + self.proper_motion_ra_deg_year * years;
+        let dec_pm_deg = self.declination_deg + self.proper_motion_dec_deg_year * years;
+
+        let mut direction = radec_to_unit_vector(ra_pm_deg, dec_pm_deg);
+
+        if self.parallax_arcsec.abs() > f64::EPSILON {
+            let parallax_rad = (self.parallax_arcsec / 3600.0).to_radians();
+            // Distance implied by the parallax, in km; the displacement is the observer's offset
+            // projected perpendicular to the line of sight, scaled by 1/distance.
+            let distance_km = AU_KM / parallax_rad.sin().max(f64::EPSILON);
+            let displacement = observer_barycentric_km / distance_km;
+            direction = (direction - displacement).normalize();
+        }
+
+        let t0_centuries =
+            (self.catalog_epoch - Epoch::from_jde_utc(J2000_JDE)).to_seconds()
+                / Unit::Day.in_seconds()
+                / DAYS_PER_JULIAN_CENTURY;
+        let t_centuries = days / DAYS_PER_JULIAN_CENTURY;
+        let (zeta_deg, z_deg, theta_deg) = precession_angles_deg(t0_centuries, t_centuries);
+
+        let precessed = rotate_z(-z_deg.to_radians())
+            * (rotate_y(theta_deg.to_radians()) * (rotate_z(-zeta_deg.to_radians()) * direction));
+
+        unit_vector_to_radec(precessed)
+    }
+}
+
+impl fmt::Display for EquatorialFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RA = {} deg, Dec = {} deg (equinox {})",
+            self.right_ascension_deg, self.declination_deg, self.catalog_epoch
+        )
+    }
+}
+
+/// Converts a right ascension/declination pair, in degrees, into a unit pointing vector.
+fn radec_to_unit_vector(ra_deg: f64, dec_deg: f64) -> Vector3<f64> {
+    let ra = ra_deg.to_radians();
+    let dec = dec_deg.to_radians();
+    Vector3::new(dec.cos() * ra.cos(), dec.cos() * ra.sin(), dec.sin())
+}
+
+/// Converts a unit pointing vector into a right ascension/declination pair, in degrees, with the
+/// right ascension wrapped to `[0, 360)`.
+fn unit_vector_to_radec(direction: Vector3<f64>) -> (f64, f64) {
+    let ra_deg = (direction.y.atan2(direction.x).to_degrees() + 360.0) % 360.0;
+    let dec_deg = direction.z.clamp(-1.0, 1.0).asin().to_degrees();
+    (ra_deg, dec_deg)
+}
+
+/// Computes the IAU 1976 precession angles ζ, z, θ, in degrees, for `t_centuries` Julian
+/// centuries elapsed from the catalog epoch to the target epoch, where the catalog epoch is
+/// itself `t0_centuries` Julian centuries away from J2000.0.
+///
+/// When `t0_centuries` is zero (a J2000.0 catalog epoch) this reduces to the textbook IAU 1976
+/// polynomials. For a non-J2000 catalog epoch (e.g. B1950), the `T0`-dependent cross terms below
+/// are required; omitting them introduces sub-arcsecond-to-arcsecond errors that grow with how
+/// far the catalog epoch sits from J2000.0. See Lieske (1979), "Precession matrix based on
+/// IAU (1976) System of Astronomical Constants".
+fn precession_angles_deg(t0_centuries: f64, t_centuries: f64) -> (f64, f64, f64) {
+    let t0 = t0_centuries;
+    let t = t_centuries;
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    let zeta_arcsec = (2306.2181 + 1.39656 * t0 - 0.000139 * t0 * t0) * t
+        + (0.30188 - 0.000344 * t0) * t2
+        + 0.017998 * t3;
+    let z_arcsec = (2306.2181 + 1.39656 * t0 - 0.000139 * t0 * t0) * t
+        + (1.09468 + 0.000066 * t0) * t2
+        + 0.018203 * t3;
+    let theta_arcsec = (2004.3109 - 0.85330 * t0 - 0.000217 * t0 * t0) * t
+        - (0.42665 + 0.000217 * t0) * t2
+        - 0.041833 * t3;
+
+    (
+        zeta_arcsec / 3600.0,
+        z_arcsec / 3600.0,
+        theta_arcsec / 3600.0,
+    )
+}
+
+/// Right-handed rotation matrix about the z axis, by `angle_rad` radians.
+fn rotate_z(angle_rad: f64) -> nalgebra::Matrix3<f64> {
+    let (s, c) = angle_rad.sin_cos();
+    nalgebra::Matrix3::new(c, -s, 0.0, s, c, 0.0, 0.0, 0.0, 1.0)
+}
+
+/// Right-handed rotation matrix about the y axis, by `angle_rad` radians.
+fn rotate_y(angle_rad: f64) -> nalgebra::Matrix3<f64> {
+    let (s, c) = angle_rad.sin_cos();
+    nalgebra::Matrix3::new(c, 0.0, s, 0.0, 1.0, 0.0, -s, 0.0, c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radec_unit_vector_round_trip() {
+        for &(ra_deg, dec_deg) in &[(0.0, 0.0), (90.0, 45.0), (270.0, -30.0), (123.4, 89.0)] {
+            let direction = radec_to_unit_vector(ra_deg, dec_deg);
+            let (ra_out, dec_out) = unit_vector_to_radec(direction);
+            assert!((ra_out - ra_deg).abs() < 1e-9, "ra: {ra_out} vs {ra_deg}");
+            assert!((dec_out - dec_deg).abs() < 1e-9, "dec: {dec_out} vs {dec_deg}");
+        }
+    }
+
+    #[test]
+    fn precession_angles_vanish_at_zero_elapsed_time() {
+        let (zeta, z, theta) = precession_angles_deg(0.0, 0.0);
+        assert_eq!(zeta, 0.0);
+        assert_eq!(z, 0.0);
+        assert_eq!(theta, 0.0);
+
+        // A non-zero T0 (non-J2000 catalog epoch) must still vanish at zero elapsed time, since
+        // every T0-dependent term above is itself multiplied by t, t^2, or t^3.
+        let (zeta, z, theta) = precession_angles_deg(-0.5, 0.0);
+        assert_eq!(zeta, 0.0);
+        assert_eq!(z, 0.0);
+        assert_eq!(theta, 0.0);
+    }
+
+    #[test]
+    fn precession_angles_include_t0_dependent_terms() {
+        // T0 = -0.5 (catalog epoch half a century before J2000.0), t = 0.7: the T0-dependent
+        // terms shift zeta by about half an arcsecond relative to the J2000-only polynomial, so
+        // the two must disagree once T0 is non-zero.
+        let (zeta_t0, _, _) = precession_angles_deg(-0.5, 0.7);
+        let (zeta_j2000, _, _) = precession_angles_deg(0.0, 0.7);
+        assert!(
+            (zeta_t0 - zeta_j2000).abs() * 3600.0 > 0.1,
+            "expected the T0 terms to shift zeta by more than 0.1 arcsec, got {} arcsec",
+            (zeta_t0 - zeta_j2000).abs() * 3600.0
+        );
+    }
+
+    #[test]
+    fn propagate_to_same_epoch_is_a_no_op_without_parallax() {
+        let catalog_epoch = Epoch::from_jde_utc(2_451_545.0); // J2000.0
+        let frame = EquatorialFrame {
+            right_ascension_deg: 88.79,
+            declination_deg: 7.41,
+            catalog_epoch,
+            proper_motion_ra_deg_year: 0.0,
+            proper_motion_dec_deg_year: 0.0,
+            parallax_arcsec: 0.0,
+            radial_velocity_km_s: 0.0,
+        };
+
+        let (ra_deg, dec_deg) = frame.propagate_to(catalog_epoch, Vector3::zeros());
+        assert!((ra_deg - frame.right_ascension_deg).abs() < 1e-9);
+        assert!((dec_deg - frame.declination_deg).abs() < 1e-9);
+    }
+
+    #[test]
+    fn propagate_to_applies_linear_proper_motion() {
+        let catalog_epoch = Epoch::from_jde_utc(2_451_545.0); // J2000.0
+        let one_year_later = Epoch::from_jde_utc(2_451_545.0 + 365.25);
+        let frame = EquatorialFrame {
+            right_ascension_deg: 10.0,
+            declination_deg: 0.0,
+            catalog_epoch,
+            proper_motion_ra_deg_year: 0.01,
+            proper_motion_dec_deg_year: 0.0,
+            parallax_arcsec: 0.0,
+            radial_velocity_km_s: 0.0,
+        };
+
+        let without_proper_motion = EquatorialFrame {
+            proper_motion_ra_deg_year: 0.0,
+            ..frame
+        };
+
+        let (ra_deg, _dec_deg) = frame.propagate_to(one_year_later, Vector3::zeros());
+        let (ra_no_pm_deg, _) =
+            without_proper_motion.propagate_to(one_year_later, Vector3::zeros());
+        // One year of precession moves this RA by about 46 arcsec (~0.0128 deg), more than the
+        // proper motion itself, so compare against the same propagation without proper motion.
+        assert!(
+            (ra_deg - ra_no_pm_deg - 0.01).abs() < 1e-3,
+            "ra: {ra_deg} vs {ra_no_pm_deg}"
+        );
+    }
+
+    #[test]
+    fn propagate_to_from_a_b1950_catalog_epoch_matches_manual_precession() {
+        // B1950.0, a catalog equinox commonly found in older star catalogs, sits at JDE
+        // 2433282.42345905 and is roughly half a Julian century before J2000.0.
+        let catalog_epoch = Epoch::from_jde_utc(2_433_282.423_459_05); // B1950.0
+        let target_epoch = Epoch::from_jde_utc(2_451_545.0); // J2000.0
+        let frame = EquatorialFrame {
+            right_ascension_deg: 41.05,
+            declination_deg: 49.23,
+            catalog_epoch,
+            proper_motion_ra_deg_year: 0.0,
+            proper_motion_dec_deg_year: 0.0,
+            parallax_arcsec: 0.0,
+            radial_velocity_km_s: 0.0,
+        };
+
+        let (ra_deg, dec_deg) = frame.propagate_to(target_epoch, Vector3::zeros());
+
+        let t0_centuries = (catalog_epoch - Epoch::from_jde_utc(J2000_JDE)).to_seconds()
+            / Unit::Day.in_seconds()
+            / DAYS_PER_JULIAN_CENTURY;
+        let t_centuries = (target_epoch - catalog_epoch).to_seconds()
+            / Unit::Day.in_seconds()
+            / DAYS_PER_JULIAN_CENTURY;
+        let (zeta_deg, z_deg, theta_deg) = precession_angles_deg(t0_centuries, t_centuries);
+        let direction = radec_to_unit_vector(frame.right_ascension_deg, frame.declination_deg);
+        let expected = rotate_z(-z_deg.to_radians())
+            * (rotate_y(theta_deg.to_radians())
+                * (rotate_z(-zeta_deg.to_radians()) * direction));
+        let (expected_ra_deg, expected_dec_deg) = unit_vector_to_radec(expected);
+
+        assert!((ra_deg - expected_ra_deg).abs() < 1e-9, "ra: {ra_deg}");
+        assert!((dec_deg - expected_dec_deg).abs() < 1e-9, "dec: {dec_deg}");
+
+        // Half a century of precession moves the position well away from the catalog
+        // coordinates, so confirm the propagated position is actually distinct from them.
+        assert!((ra_deg - frame.right_ascension_deg).abs() * 3600.0 > 1.0);
+    }
+}
@@ -9,8 +9,18 @@
  */
 
 use super::{celestial_frame::CelestialFrame, CelestialFrameTrait, Frame, FrameTrait};
-use crate::{context::Context, prelude::AniseError, shapes::ellipsoid::Ellipsoid, NaifId};
+use crate::{
+    context::Context, prelude::AniseError, shapes::ellipsoid::Ellipsoid,
+    structure::gravity_field::GravityField, NaifId,
+};
 use core::fmt;
+use log::error;
+use nalgebra::Vector3;
+
+/// Maximum number of iterations allowed when solving the iterative geodetic to ECEF inverse.
+const MAX_ITER_ECEF_TO_GEODETIC: u8 = 10;
+/// Convergence tolerance, in radians, on the latitude estimate of the iterative inverse.
+const ECEF_TO_GEODETIC_TOLERANCE_RAD: f64 = 1e-12;
 
 /// Defines a Celestial Frame kind, which is a Frame that also defines a standard gravitational parameter
 pub trait GeodeticFrameTrait: CelestialFrameTrait {
@@ -22,14 +32,225 @@ pub trait GeodeticFrameTrait: CelestialFrameTrait {
     fn flattening(&self) -> f64;
     /// Returns the average angular velocity of this frame
     fn angular_velocity_deg_s(&self) -> f64;
+
+    /// Eccentricity squared of the reference ellipsoid, i.e. `e² = 2f - f²`.
+    fn eccentricity_squared(&self) -> f64 {
+        let f = self.flattening();
+        2.0 * f - f * f
+    }
+
+    /// Converts a geodetic latitude, longitude (both in degrees), and height above the
+    /// reference ellipsoid (in kilometers) into a body-fixed Cartesian (ECEF-like) position.
+    ///
+    /// This is the direct problem of geodesy, mirroring octave-mapping's `geodetic2ecef`.
+    fn geodetic_to_cartesian(&self, latitude_deg: f64, longitude_deg: f64, height_km: f64) -> Vector3<f64> {
+        let a = self.semi_major_radius_km();
+        let e2 = self.eccentricity_squared();
+
+        let phi = latitude_deg.to_radians();
+        let lambda = longitude_deg.to_radians();
+
+        let sin_phi = phi.sin();
+        let cos_phi = phi.cos();
+
+        let n = a / (1.0 - e2 * sin_phi * sin_phi).sqrt();
+
+        let x = (n + height_km) * cos_phi * lambda.cos();
+        let y = (n + height_km) * cos_phi * lambda.sin();
+        let z = (n * (1.0 - e2) + height_km) * sin_phi;
+
+        Vector3::new(x, y, z)
+    }
+
+    /// Converts a body-fixed Cartesian (ECEF-like) position, in kilometers, into geodetic
+    /// latitude, longitude (both in degrees), and height above the reference ellipsoid (in km).
+    ///
+    /// This is the inverse problem of geodesy, mirroring octave-mapping's `ecef2geodetic`, and
+    /// is solved iteratively since there is no closed-form solution.
+    fn cartesian_to_geodetic(&self, ecef_km: Vector3<f64>) -> (f64, f64, f64) {
+        let a = self.semi_major_radius_km();
+        let f = self.flattening();
+        let b = a * (1.0 - f);
+        let e2 = self.eccentricity_squared();
+
+        let (x, y, z) = (ecef_km.x, ecef_km.y, ecef_km.z);
+
+        let lambda = y.atan2(x);
+        let p = (x * x + y * y).sqrt();
+
+        // Handle the polar singularity where the longitude and `p / cos(phi)` are undefined.
+        if p < f64::EPSILON {
+            let phi = if z >= 0.0 {
+                core::f64::consts::FRAC_PI_2
+            } else {
+                -core::f64::consts::FRAC_PI_2
+            };
+            let height_km = z.abs() - b;
+            return (phi.to_degrees(), lambda.to_degrees(), height_km);
+        }
+
+        let mut phi = z.atan2(p * (1.0 - e2));
+        let mut height_km = 0.0;
+
+        for _ in 0..MAX_ITER_ECEF_TO_GEODETIC {
+            let sin_phi = phi.sin();
+            let n = a / (1.0 - e2 * sin_phi * sin_phi).sqrt();
+            height_km = p / phi.cos() - n;
+            let new_phi = z.atan2(p * (1.0 - e2 * n / (n + height_km)));
+
+            let converged = (new_phi - phi).abs() < ECEF_TO_GEODETIC_TOLERANCE_RAD;
+            phi = new_phi;
+            if converged {
+                break;
+            }
+        }
+
+        (phi.to_degrees(), lambda.to_degrees(), height_km)
+    }
+
+    /// Computes the azimuth (deg, clockwise from North, in [0, 360)), elevation (deg above the
+    /// local horizon), and range (km) of `target_km`, as seen from an observer at geodetic
+    /// latitude `observer_latitude_deg`, longitude `observer_longitude_deg`, and height
+    /// `observer_height_km`. Both positions must be expressed in this frame's body-fixed frame.
+    ///
+    /// This mirrors the topocentric look-angle computation used by
+    /// `Almanac::azimuth_elevation_range_sez` and octave-mapping's `ecef2aer`.
+    fn azimuth_elevation_range(
+        &self,
+        observer_latitude_deg: f64,
+        observer_longitude_deg: f64,
+        observer_height_km: f64,
+        target_km: Vector3<f64>,
+    ) -> (f64, f64, f64) {
+        let observer_km = self.geodetic_to_cartesian(
+            observer_latitude_deg,
+            observer_longitude_deg,
+            observer_height_km,
+        );
+
+        let los_km = target_km - observer_km;
+        let range_km = los_km.norm();
+
+        let phi = observer_latitude_deg.to_radians();
+        let lambda = observer_longitude_deg.to_radians();
+
+        let (sin_phi, cos_phi) = (phi.sin(), phi.cos());
+        let (sin_lambda, cos_lambda) = (lambda.sin(), lambda.cos());
+
+        let east = Vector3::new(-sin_lambda, cos_lambda, 0.0);
+        let north = Vector3::new(-sin_phi * cos_lambda, -sin_phi * sin_lambda, cos_phi);
+        let up = Vector3::new(cos_phi * cos_lambda, cos_phi * sin_lambda, sin_phi);
+
+        let elevation_deg = (los_km.dot(&up) / range_km)
+            .clamp(-1.0, 1.0)
+            .asin()
+            .to_degrees();
+        let azimuth_deg = los_km.dot(&east).atan2(los_km.dot(&north)).to_degrees();
+        let azimuth_deg = (azimuth_deg + 360.0) % 360.0;
+
+        (azimuth_deg, elevation_deg, range_km)
+    }
+
+    /// Computes the surface area, in square kilometers, of the quadrangle on the reference
+    /// ellipsoid bounded by latitudes `lat1_deg` and `lat2_deg` and longitudes `lon1_deg` and
+    /// `lon2_deg`, mirroring octave-mapping's `areaquad`.
+    ///
+    /// This integrates the authalic (equal-area) surface element of the ellipsoid. In the
+    /// spherical limit (`flattening` ≈ 0), the closed form `a²·Δλ·(sinφ₂ - sinφ₁)` is used
+    /// instead to avoid a division by the vanishing eccentricity.
+    fn area_km2(&self, lat1_deg: f64, lon1_deg: f64, lat2_deg: f64, lon2_deg: f64) -> f64 {
+        let a = self.semi_major_radius_km();
+        let e2 = self.eccentricity_squared();
+        let delta_lambda = (lon2_deg - lon1_deg).to_radians();
+
+        let phi1 = lat1_deg.to_radians();
+        let phi2 = lat2_deg.to_radians();
+
+        if e2.abs() < f64::EPSILON {
+            return a * a * delta_lambda * (phi2.sin() - phi1.sin());
+        }
+
+        let e = e2.sqrt();
+        let f = self.flattening();
+        let b = a * (1.0 - f);
+
+        let zone = |phi: f64| -> f64 {
+            let sin_phi = phi.sin();
+            sin_phi / (1.0 - e2 * sin_phi * sin_phi)
+                + (1.0 / (2.0 * e)) * ((1.0 + e * sin_phi) / (1.0 - e * sin_phi)).ln()
+        };
+
+        (delta_lambda * b * b / 2.0) * (zone(phi2) - zone(phi1))
+    }
+
+    /// Computes the geodesic distance, in kilometers, between two points on the reference
+    /// ellipsoid given as (latitude, longitude) pairs in degrees.
+    ///
+    /// This uses the spherical haversine formula on the ellipsoid's mean equatorial radius
+    /// (`mean_equatorial_radius_km`), ignoring the flattening, which is
+    /// sufficiently accurate for footprint and coverage computations; a full ellipsoidal
+    /// geodesic solver (e.g. Vincenty's method) can be substituted later without changing this
+    /// signature.
+    fn geodesic_distance_km(&self, lat1_deg: f64, lon1_deg: f64, lat2_deg: f64, lon2_deg: f64) -> f64 {
+        let r = self.mean_equatorial_radius_km();
+
+        let phi1 = lat1_deg.to_radians();
+        let phi2 = lat2_deg.to_radians();
+        let delta_phi = (lat2_deg - lat1_deg).to_radians();
+        let delta_lambda = (lon2_deg - lon1_deg).to_radians();
+
+        let sin_half_dphi = (delta_phi / 2.0).sin();
+        let sin_half_dlambda = (delta_lambda / 2.0).sin();
+
+        let haversine = sin_half_dphi * sin_half_dphi
+            + phi1.cos() * phi2.cos() * sin_half_dlambda * sin_half_dlambda;
+
+        let central_angle = 2.0 * haversine.sqrt().asin();
+
+        r * central_angle
+    }
 }
 
 /// A GeodeticFrame is a Celestial Frame whose equatorial and semi major radii are defined.
-#[derive(Copy, Clone, Debug, PartialEq)]
+///
+/// It may optionally carry a spherical-harmonic `gravity_field` (e.g. an EGM96-style model),
+/// which unlocks geoid undulation and higher-fidelity gravity acceleration computations.
+#[derive(Clone, Debug, PartialEq)]
 pub struct GeodeticFrame {
     pub celestial_frame: CelestialFrame,
     pub shape: Ellipsoid,
     pub angular_velocity_deg: f64,
+    pub gravity_field: Option<GravityField>,
+}
+
+impl GeodeticFrame {
+    /// Attaches a spherical-harmonic gravity field to this frame, enabling
+    /// [`Self::geoid_undulation_km`].
+    pub fn with_gravity_field(mut self, gravity_field: GravityField) -> Self {
+        self.gravity_field = Some(gravity_field);
+        self
+    }
+
+    /// Computes the geoid undulation, in kilometers, at the given geodetic latitude/longitude
+    /// (degrees), following octave-mapping's `egm96geoid`.
+    ///
+    /// The normal gravity `γ` used in the Bruns formula `N = T / γ` is approximated here by the
+    /// point-mass gravity `μ / r²` at the evaluation radius; this is accurate to the precision
+    /// needed for geoid heights, which are a small correction on top of the reference ellipsoid.
+    ///
+    /// # Errors
+    /// Returns `AniseError::ParameterNotSpecified` if this frame has no attached gravity field.
+    pub fn geoid_undulation_km(&self, latitude_deg: f64, longitude_deg: f64) -> Result<f64, AniseError> {
+        let gravity_field = self
+            .gravity_field
+            .clone()
+            .ok_or(AniseError::ParameterNotSpecified)?;
+
+        let r_km = self.geodetic_to_cartesian(latitude_deg, longitude_deg, 0.0).norm();
+        let gamma_km_s2 = self.mu_km3_s2() / (r_km * r_km);
+
+        gravity_field.undulation_km(latitude_deg, longitude_deg, r_km, gamma_km_s2)
+    }
 }
 
 impl FrameTrait for GeodeticFrame {
@@ -99,26 +320,190 @@ impl<'a> Context<'a> {
     /// Tries to find the geodetic frame data given the ephemeris center name, the orientation name, and the name of the planetary constants
     pub fn geodetic_frame_from(
         &self,
-        _ephemeris_name: &'a str,
-        _orientation_name: &'a str,
-        _planetary_constants_name: &'a str,
+        ephemeris_name: &'a str,
+        orientation_name: &'a str,
+        planetary_constants_name: &'a str,
+    ) -> Result<GeodeticFrame, AniseError> {
+        let constants = self.planetary_constants_from_name(planetary_constants_name)?;
+
+        if constants.shape.is_none() {
+            error!("no shape data associated with {planetary_constants_name}");
+            return Err(AniseError::ParameterNotSpecified);
+        }
+
+        Ok(GeodeticFrame {
+            celestial_frame: CelestialFrame {
+                frame: Frame::from_ephemeris_orientation_names(ephemeris_name, orientation_name),
+                mu_km3_s2: constants.mu_km3_s2,
+            },
+            shape: constants.shape.unwrap(),
+            // Not every body's planetary constants carry a rotation rate (just like `shape`
+            // above), so this mirrors that field's `Option` and falls back to the prior
+            // hardcoded default rather than failing the whole frame lookup.
+            angular_velocity_deg: constants.angular_velocity_deg.unwrap_or(0.0),
+            gravity_field: None,
+        })
+    }
+
+    /// Tries to find the geodetic frame data as with [`Self::geodetic_frame_from`], additionally
+    /// attaching the named spherical-harmonic gravity field dataset (e.g. `"EGM96"`).
+    pub fn geodetic_frame_with_gravity_field(
+        &self,
+        ephemeris_name: &'a str,
+        orientation_name: &'a str,
+        planetary_constants_name: &'a str,
+        gravity_field_name: &'a str,
     ) -> Result<GeodeticFrame, AniseError> {
-        todo!()
-        // let constants = self.planetary_constants_from_name(planetary_constants_name)?;
-
-        // if constants.shape.is_none() {
-        //     error!("no shape data associated with {planetary_constants_name}");
-        //     return Err(AniseError::ParameterNotSpecified);
-        // }
-
-        // // TODO: Figure out how to specify / where to find the angular velocity. And maybe it shouldn't exist!
-        // Ok(GeodeticFrame {
-        //     celestial_frame: CelestialFrame {
-        //         frame: Frame::from_ephemeris_orientation_names(ephemeris_name, orientation_name),
-        //         mu_km3_s2: constants.mu_km3_s2,
-        //     },
-        //     shape: constants.shape.unwrap(),
-        //     angular_velocity_deg: 0.0,
-        // })
-    }
-}
\ No newline at end of file
+        let frame =
+            self.geodetic_frame_from(ephemeris_name, orientation_name, planetary_constants_name)?;
+        let gravity_field = self.gravity_field_from_name(gravity_field_name)?;
+        Ok(frame.with_gravity_field(gravity_field))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// WGS84-like test ellipsoid, independent of the `Ellipsoid`/`CelestialFrame` types so the
+    /// default trait methods on `GeodeticFrameTrait` can be exercised without constructing a
+    /// full `GeodeticFrame`.
+    struct TestEllipsoid {
+        a_km: f64,
+        f: f64,
+    }
+
+    impl CelestialFrameTrait for TestEllipsoid {
+        fn mu_km3_s2(&self) -> f64 {
+            398_600.4418
+        }
+    }
+
+    impl GeodeticFrameTrait for TestEllipsoid {
+        fn mean_equatorial_radius_km(&self) -> f64 {
+            self.a_km
+        }
+
+        fn semi_major_radius_km(&self) -> f64 {
+            self.a_km
+        }
+
+        fn flattening(&self) -> f64 {
+            self.f
+        }
+
+        fn angular_velocity_deg_s(&self) -> f64 {
+            0.004178073
+        }
+    }
+
+    const WGS84: TestEllipsoid = TestEllipsoid {
+        a_km: 6378.137,
+        f: 1.0 / 298.257223563,
+    };
+
+    #[test]
+    fn geodetic_to_cartesian_equator_prime_meridian() {
+        // At (0, 0, 0), the ECEF position is exactly on the equatorial radius, on the X axis.
+        let ecef = WGS84.geodetic_to_cartesian(0.0, 0.0, 0.0);
+        assert!((ecef.x - WGS84.a_km).abs() < 1e-9);
+        assert!(ecef.y.abs() < 1e-9);
+        assert!(ecef.z.abs() < 1e-9);
+    }
+
+    #[test]
+    fn geodetic_to_cartesian_pole() {
+        // At the north pole, height above the ellipsoid is measured along the semi-minor axis.
+        let b_km = WGS84.a_km * (1.0 - WGS84.f);
+        let ecef = WGS84.geodetic_to_cartesian(90.0, 0.0, 0.0);
+        assert!(ecef.x.abs() < 1e-9);
+        assert!(ecef.y.abs() < 1e-9);
+        assert!((ecef.z - b_km).abs() < 1e-6);
+    }
+
+    #[test]
+    fn geodetic_ecef_round_trip() {
+        for &(lat_deg, lon_deg, h_km) in &[
+            (0.0, 0.0, 0.0),
+            (45.0, 30.0, 1.0),
+            (-60.0, 170.0, 400.0),
+            (89.999, 12.0, 0.5),
+        ] {
+            let ecef = WGS84.geodetic_to_cartesian(lat_deg, lon_deg, h_km);
+            let (lat_out, lon_out, h_out) = WGS84.cartesian_to_geodetic(ecef);
+            assert!((lat_out - lat_deg).abs() < 1e-7, "lat: {lat_out} vs {lat_deg}");
+            assert!((lon_out - lon_deg).abs() < 1e-7, "lon: {lon_out} vs {lon_deg}");
+            assert!((h_out - h_km).abs() < 1e-6, "h: {h_out} vs {h_km}");
+        }
+    }
+
+    #[test]
+    fn cartesian_to_geodetic_polar_singularity() {
+        let (lat_deg, lon_deg, h_km) = WGS84.cartesian_to_geodetic(Vector3::new(0.0, 0.0, 7000.0));
+        assert!((lat_deg - 90.0).abs() < 1e-9);
+        assert_eq!(lon_deg, 0.0);
+        let b_km = WGS84.a_km * (1.0 - WGS84.f);
+        assert!((h_km - (7000.0 - b_km)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn azimuth_elevation_range_directly_overhead() {
+        // A target directly above the observer (along the local geodetic "up", i.e. the same
+        // latitude/longitude 500 km higher) must read elevation = 90 deg, with the range equal
+        // to the altitude difference.
+        let target = WGS84.geodetic_to_cartesian(10.0, 20.0, 500.0);
+
+        let (_az_deg, el_deg, range_km) = WGS84.azimuth_elevation_range(10.0, 20.0, 0.0, target);
+        assert!((el_deg - 90.0).abs() < 1e-6);
+        assert!((range_km - 500.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn azimuth_elevation_range_due_north_on_horizon() {
+        // A target due north of the observer, on the local horizon, must read azimuth = 0 deg
+        // and elevation ~= 0 deg.
+        let observer = WGS84.geodetic_to_cartesian(0.0, 0.0, 0.0);
+        let target = WGS84.geodetic_to_cartesian(1.0, 0.0, 0.0);
+
+        let (az_deg, el_deg, _range_km) = WGS84.azimuth_elevation_range(0.0, 0.0, 0.0, target);
+        assert!(az_deg.abs() < 1.0, "azimuth: {az_deg}");
+        assert!(el_deg.abs() < 1.0, "elevation: {el_deg}");
+    }
+
+    const SPHERE: TestEllipsoid = TestEllipsoid {
+        a_km: 6371.0,
+        f: 0.0,
+    };
+
+    #[test]
+    fn area_km2_whole_sphere_matches_4_pi_r_squared() {
+        let area = SPHERE.area_km2(-90.0, 0.0, 90.0, 360.0);
+        let expected = 4.0 * core::f64::consts::PI * SPHERE.a_km * SPHERE.a_km;
+        assert!(
+            (area - expected).abs() / expected < 1e-9,
+            "area: {area} vs {expected}"
+        );
+    }
+
+    #[test]
+    fn area_km2_ellipsoid_close_to_sphere_limit() {
+        // A near-spherical ellipsoid's quadrangle area should match the exact sphere formula
+        // to within the small flattening-induced correction.
+        let sphere_area = SPHERE.area_km2(0.0, 0.0, 30.0, 90.0);
+        let wgs84_area = WGS84.area_km2(0.0, 0.0, 30.0, 90.0);
+        assert!((sphere_area - wgs84_area).abs() / sphere_area < 1e-2);
+    }
+
+    #[test]
+    fn geodesic_distance_km_quarter_great_circle() {
+        // Two points 90 degrees apart on the equator are a quarter of the great circle apart.
+        let distance = SPHERE.geodesic_distance_km(0.0, 0.0, 0.0, 90.0);
+        let expected = core::f64::consts::FRAC_PI_2 * SPHERE.a_km;
+        assert!((distance - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn geodesic_distance_km_same_point_is_zero() {
+        assert_eq!(SPHERE.geodesic_distance_km(12.0, 34.0, 12.0, 34.0), 0.0);
+    }
+}
@@ -0,0 +1,398 @@
+/*
+ * ANISE Toolkit
+ * Copyright (C) 2021-2022 Christopher Rabotin <christopher.rabotin@gmail.com> et al. (cf. AUTHORS.md)
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ *
+ * Documentation: https://nyxspace.com/
+ */
+use alloc::sync::Arc;
+use der::{
+    asn1::OctetStringRef,
+    Decode, Encode, Reader, Writer,
+};
+
+use crate::prelude::AniseError;
+
+/// Defensive cap on `degree`/`order`, equal to the maximum degree of EGM2008 (2190), so that
+/// models up to that degree are accepted. This keeps a corrupted or
+/// crafted file's `degree` from forcing an unbounded `(degree+1)×(degree+1)` allocation in
+/// [`GravityField::undulation_km`].
+const MAX_SUPPORTED_DEGREE: u16 = 2190;
+
+/// A `GravityField` stores fully normalized spherical-harmonic gravity coefficients (`C_nm`,
+/// `S_nm`), e.g. EGM96-style models, so that a `GeodeticFrame` can compute geoid undulations
+/// and gravity accelerations beyond the simple point-mass (two-body) approximation.
+///
+/// Coefficients are stored degree-major, i.e. row `n` holds the `n + 1` order-`m` coefficients
+/// `(C_n0, S_n0), ..., (C_nn, S_nn)`, flattened into `cs_coefficients`.
+///
+/// Unlike the other zero-copy, borrowed DER types in this crate, `cs_coefficients` is owned
+/// (`Arc`-backed) rather than borrowed from the decoder's input buffer. This lets a
+/// `GravityField` be attached to a `GeodeticFrame` (and cloned freely, e.g. across threads)
+/// without forcing every consumer of `GeodeticFrame` to propagate a new lifetime parameter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GravityField {
+    /// Gravitational parameter used to normalize this model, in km^3/s^2.
+    pub mu_km3_s2: f64,
+    /// Reference (equatorial) radius used to normalize this model, in kilometers.
+    pub radius_km: f64,
+    /// Maximum degree of the coefficients stored in `cs_coefficients`. Storage is always the
+    /// full triangular set of `(C_nm, S_nm)` pairs for `0 <= m <= n <= degree`, independent of
+    /// `order` below.
+    pub degree: u16,
+    /// Maximum order summed over when evaluating the potential (`order <= degree`). This only
+    /// truncates which columns of each row are *used* in [`GravityField::undulation_km`]; the
+    /// higher-order coefficients, if any, are still present in `cs_coefficients`.
+    pub order: u16,
+    /// Flattened, degree-major `(C_nm, S_nm)` pairs of normalized coefficients, little-endian
+    /// `f64` pairs packed as raw bytes.
+    pub cs_coefficients: Arc<[u8]>,
+}
+
+impl GravityField {
+    /// Checks that `degree`/`order` are within [`MAX_SUPPORTED_DEGREE`] and that
+    /// `cs_coefficients` holds exactly the triangular number of `(C_nm, S_nm)` pairs the
+    /// declared `degree` implies, before any per-coefficient access is attempted.
+    ///
+    /// # Errors
+    /// Returns `AniseError::IndexingError` if `degree` exceeds [`MAX_SUPPORTED_DEGREE`],
+    /// `order > degree`, or `cs_coefficients` does not match the expected length.
+    pub fn validate(&self) -> Result<(), AniseError> {
+        if self.degree > MAX_SUPPORTED_DEGREE || self.order > self.degree {
+            return Err(AniseError::IndexingError);
+        }
+
+        if self.cs_coefficients.len() != expected_cs_coefficients_len(self.degree) {
+            return Err(AniseError::IndexingError);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the normalized `(C_nm, S_nm)` pair for the given degree `n` and order `m`.
+    ///
+    /// # Errors
+    /// Returns `AniseError::IndexingError` if `n > self.degree`, `m > n`, or if
+    /// `cs_coefficients` is too short to hold the requested pair (e.g. a corrupt file whose
+    /// `degree`/`order` do not match its coefficient buffer).
+    pub fn cs_nm(&self, n: u16, m: u16) -> Result<(f64, f64), AniseError> {
+        if n > self.degree || m > n {
+            return Err(AniseError::IndexingError);
+        }
+
+        // Row `n` starts after `1 + 2 + ... + n` prior (C, S) pairs, i.e. `n * (n + 1) / 2`.
+        let pair_index = (n as usize) * (n as usize + 1) / 2 + m as usize;
+        let byte_offset = pair_index * 16;
+
+        if byte_offset + 16 > self.cs_coefficients.len() {
+            return Err(AniseError::IndexingError);
+        }
+
+        let c = f64::from_le_bytes(
+            self.cs_coefficients[byte_offset..byte_offset + 8]
+                .try_into()
+                .map_err(|_| AniseError::IndexingError)?,
+        );
+        let s = f64::from_le_bytes(
+            self.cs_coefficients[byte_offset + 8..byte_offset + 16]
+                .try_into()
+                .map_err(|_| AniseError::IndexingError)?,
+        );
+
+        Ok((c, s))
+    }
+
+    /// Computes the geoid undulation (height of the geoid above the reference ellipsoid, in
+    /// kilometers) at the given geodetic latitude/longitude (degrees) and normal gravity
+    /// `gamma_km_s2` (km/s^2), following the Bruns formula `N = T / gamma`.
+    pub fn undulation_km(
+        &self,
+        latitude_deg: f64,
+        longitude_deg: f64,
+        r_km: f64,
+        gamma_km_s2: f64,
+    ) -> Result<f64, AniseError> {
+        // Must happen before `fully_normalized_legendre` allocates its `(degree+1)^2` table.
+        self.validate()?;
+
+        let phi = latitude_deg.to_radians();
+        let lambda = longitude_deg.to_radians();
+        let sin_phi = phi.sin();
+
+        let p_nm = fully_normalized_legendre(self.degree, sin_phi);
+
+        let mut disturbing_potential = 0.0;
+        let ratio = self.radius_km / r_km;
+        let mut ratio_n = ratio * ratio; // (a/r)^2, since n starts at 2
+
+        for n in 2..=self.degree {
+            let mut sum_m = 0.0;
+            for m in 0..=n.min(self.order) {
+                let (c_nm, s_nm) = self.cs_nm(n, m)?;
+                let m_lambda = m as f64 * lambda;
+                sum_m += (c_nm * m_lambda.cos() + s_nm * m_lambda.sin()) * p_nm[n as usize][m as usize];
+            }
+            disturbing_potential += ratio_n * sum_m;
+            ratio_n *= ratio;
+        }
+        disturbing_potential *= self.mu_km3_s2 / r_km;
+
+        Ok(disturbing_potential / gamma_km_s2)
+    }
+}
+
+/// Returns the number of bytes `cs_coefficients` must hold for the given `degree`: the
+/// triangular number of `(C_nm, S_nm)` pairs for `0 <= m <= n <= degree`, 16 bytes each.
+fn expected_cs_coefficients_len(degree: u16) -> usize {
+    let degree = degree as usize;
+    (degree + 1) * (degree + 2) / 2 * 16
+}
+
+/// Computes the fully normalized associated Legendre functions `P_nm(x)` for all `0 <= m <= n
+/// <= degree`, using the standard forward column/row recurrences.
+fn fully_normalized_legendre(degree: u16, x: f64) -> Vec<Vec<f64>> {
+    let degree = degree as usize;
+    let mut p = vec![vec![0.0; degree + 1]; degree + 1];
+
+    p[0][0] = 1.0;
+    if degree == 0 {
+        return p;
+    }
+    p[1][0] = (3.0f64).sqrt() * x;
+    p[1][1] = (3.0f64).sqrt() * (1.0 - x * x).sqrt();
+
+    for n in 2..=degree {
+        for m in 0..=n {
+            if m == n {
+                // Sectorial recurrence: P_nn from P_(n-1)(n-1).
+                p[n][m] = ((2.0 * n as f64 + 1.0) / (2.0 * n as f64)).sqrt()
+                    * (1.0 - x * x).sqrt()
+                    * p[n - 1][m - 1];
+            } else {
+                let a_nm = (((2.0 * n as f64 + 1.0) * (2.0 * n as f64 - 1.0))
+                    / ((n as f64 - m as f64) * (n as f64 + m as f64)))
+                    .sqrt();
+                let b_nm = ((2.0 * n as f64 + 1.0) * (n as f64 + m as f64 - 1.0) * (n as f64 - m as f64 - 1.0)
+                    / ((n as f64 - m as f64) * (n as f64 + m as f64) * (2.0 * n as f64 - 3.0)))
+                    .sqrt();
+                let prev = if n >= 2 { p[n - 2][m] } else { 0.0 };
+                p[n][m] = a_nm * x * p[n - 1][m] - b_nm * prev;
+            }
+        }
+    }
+
+    p
+}
+
+impl Encode for GravityField {
+    fn encoded_len(&self) -> der::Result<der::Length> {
+        self.mu_km3_s2.encoded_len()?
+            + self.radius_km.encoded_len()?
+            + OctetStringRef::new(&self.degree.to_be_bytes())?.encoded_len()?
+            + OctetStringRef::new(&self.order.to_be_bytes())?.encoded_len()?
+            + OctetStringRef::new(&self.cs_coefficients)?.encoded_len()?
+    }
+
+    fn encode(&self, encoder: &mut dyn Writer) -> der::Result<()> {
+        self.mu_km3_s2.encode(encoder)?;
+        self.radius_km.encode(encoder)?;
+        OctetStringRef::new(&self.degree.to_be_bytes())?.encode(encoder)?;
+        OctetStringRef::new(&self.order.to_be_bytes())?.encode(encoder)?;
+        OctetStringRef::new(&self.cs_coefficients)?.encode(encoder)
+    }
+}
+
+impl<'a> Decode<'a> for GravityField {
+    fn decode<R: Reader<'a>>(decoder: &mut R) -> der::Result<Self> {
+        let mu_km3_s2 = decoder.decode()?;
+        let radius_km = decoder.decode()?;
+        // Stored as fixed-width two-byte octet strings (not a DER `Uint`, whose content octets
+        // are minimally encoded and would drop the leading zero byte for any value < 256).
+        let degree_bytes = decoder.decode::<OctetStringRef<'a>>()?;
+        let order_bytes = decoder.decode::<OctetStringRef<'a>>()?;
+        // Copied into an owned buffer (see the struct doc) rather than borrowed from the
+        // decoder, so that `GravityField` carries no lifetime of its own.
+        let cs_coefficients: Arc<[u8]> = decoder.decode::<OctetStringRef<'a>>()?.as_bytes().into();
+
+        let degree = u16::from_be_bytes(
+            degree_bytes
+                .as_bytes()
+                .try_into()
+                .map_err(|_| der::Tag::OctetString.value_error())?,
+        );
+        let order = u16::from_be_bytes(
+            order_bytes
+                .as_bytes()
+                .try_into()
+                .map_err(|_| der::Tag::OctetString.value_error())?,
+        );
+
+        // Reject a `degree` beyond any real model, or a coefficient buffer that doesn't match
+        // it, right here: `undulation_km`/`fully_normalized_legendre` trust `degree` to size an
+        // allocation, so a corrupt or crafted file must never get that far with a bogus value.
+        if degree > MAX_SUPPORTED_DEGREE
+            || order > degree
+            || cs_coefficients.len() != expected_cs_coefficients_len(degree)
+        {
+            return Err(der::Tag::OctetString.value_error());
+        }
+
+        Ok(Self {
+            mu_km3_s2,
+            radius_km,
+            degree,
+            order,
+            cs_coefficients,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack_cs(pairs: &[(f64, f64)]) -> Arc<[u8]> {
+        let mut bytes = Vec::with_capacity(pairs.len() * 16);
+        for (c, s) in pairs {
+            bytes.extend_from_slice(&c.to_le_bytes());
+            bytes.extend_from_slice(&s.to_le_bytes());
+        }
+        bytes.into()
+    }
+
+    #[test]
+    fn cs_nm_reads_back_packed_pairs() {
+        // Degree 2 needs 1 + 2 + 3 = 6 (C, S) pairs: (0,0), (1,0), (1,1), (2,0), (2,1), (2,2).
+        let field = GravityField {
+            mu_km3_s2: 398_600.4418,
+            radius_km: 6378.137,
+            degree: 2,
+            order: 2,
+            cs_coefficients: pack_cs(&[
+                (1.0, 0.0),
+                (2.0, 2.5),
+                (3.0, 3.5),
+                (4.0, 4.5),
+                (5.0, 5.5),
+                (6.0, 6.5),
+            ]),
+        };
+
+        assert_eq!(field.cs_nm(0, 0).unwrap(), (1.0, 0.0));
+        assert_eq!(field.cs_nm(2, 1).unwrap(), (5.0, 5.5));
+        assert_eq!(field.cs_nm(2, 2).unwrap(), (6.0, 6.5));
+    }
+
+    #[test]
+    fn cs_nm_rejects_out_of_range_indices() {
+        let field = GravityField {
+            mu_km3_s2: 1.0,
+            radius_km: 1.0,
+            degree: 1,
+            order: 1,
+            cs_coefficients: pack_cs(&[(1.0, 0.0), (2.0, 0.0), (3.0, 0.0)]),
+        };
+
+        assert_eq!(field.cs_nm(2, 0), Err(AniseError::IndexingError));
+        assert_eq!(field.cs_nm(1, 2), Err(AniseError::IndexingError));
+    }
+
+    #[test]
+    fn cs_nm_rejects_buffer_shorter_than_declared_degree() {
+        // `degree` claims row 2 exists, but the buffer only actually holds row 0 and row 1.
+        let field = GravityField {
+            mu_km3_s2: 1.0,
+            radius_km: 1.0,
+            degree: 2,
+            order: 2,
+            cs_coefficients: pack_cs(&[(1.0, 0.0), (2.0, 0.0), (3.0, 0.0)]),
+        };
+
+        assert_eq!(field.cs_nm(2, 0), Err(AniseError::IndexingError));
+    }
+
+    #[test]
+    fn fully_normalized_legendre_p00_is_one() {
+        let p = fully_normalized_legendre(4, 0.3);
+        assert_eq!(p[0][0], 1.0);
+    }
+
+    #[test]
+    fn undulation_km_is_zero_without_any_degree_2_plus_term() {
+        // A degree-1 field has no terms in the `n in 2..=degree` sum, so the disturbing
+        // potential (and thus the undulation) is exactly zero everywhere.
+        let field = GravityField {
+            mu_km3_s2: 398_600.4418,
+            radius_km: 6378.137,
+            degree: 1,
+            order: 1,
+            cs_coefficients: pack_cs(&[(1.0, 0.0), (2.0, 0.0), (3.0, 0.0)]),
+        };
+
+        let undulation = field.undulation_km(10.0, 20.0, 6378.137, 0.00981).unwrap();
+        assert_eq!(undulation, 0.0);
+    }
+
+    #[test]
+    fn validate_rejects_degree_above_cap() {
+        let field = GravityField {
+            mu_km3_s2: 1.0,
+            radius_km: 1.0,
+            degree: MAX_SUPPORTED_DEGREE + 1,
+            order: 0,
+            cs_coefficients: Arc::from(&[][..]),
+        };
+
+        assert_eq!(field.validate(), Err(AniseError::IndexingError));
+    }
+
+    #[test]
+    fn validate_rejects_coefficient_buffer_mismatched_with_degree() {
+        // `degree` implies 6 pairs (96 bytes), but only 3 pairs are actually present.
+        let field = GravityField {
+            mu_km3_s2: 1.0,
+            radius_km: 1.0,
+            degree: 2,
+            order: 2,
+            cs_coefficients: pack_cs(&[(1.0, 0.0), (2.0, 0.0), (3.0, 0.0)]),
+        };
+
+        assert_eq!(field.validate(), Err(AniseError::IndexingError));
+    }
+
+    #[test]
+    fn undulation_km_rejects_huge_declared_degree_without_allocating() {
+        // A `degree` this large would allocate gigabytes in `fully_normalized_legendre` if not
+        // caught by `validate()` first; the (empty) buffer obviously can't back it either way.
+        let field = GravityField {
+            mu_km3_s2: 1.0,
+            radius_km: 1.0,
+            degree: u16::MAX,
+            order: u16::MAX,
+            cs_coefficients: Arc::from(&[][..]),
+        };
+
+        assert_eq!(
+            field.undulation_km(0.0, 0.0, 1.0, 1.0),
+            Err(AniseError::IndexingError)
+        );
+    }
+
+    #[test]
+    fn decode_rejects_degree_above_cap() {
+        // The coefficient buffer is deliberately left empty: the oversized `degree` alone must
+        // be enough for `decode` to reject the file, with no dependence on its (real) size.
+        let oversized = GravityField {
+            mu_km3_s2: 398_600.4418,
+            radius_km: 6378.137,
+            degree: MAX_SUPPORTED_DEGREE + 1,
+            order: 0,
+            cs_coefficients: Arc::from(&[][..]),
+        };
+
+        let der_bytes = oversized.to_der().unwrap();
+        assert!(GravityField::from_der(&der_bytes).is_err());
+    }
+}
@@ -8,11 +8,17 @@
  * Documentation: https://nyxspace.com/
  */
 use core::fmt;
-use der::{asn1::Utf8StringRef, Decode, Encode, Reader, Writer};
+use der::{
+    asn1::{OctetStringRef, Utf8StringRef},
+    Decode, Encode, Reader, Writer,
+};
 use hifitime::Epoch;
 
 use super::{semver::Semver, ANISE_VERSION};
 
+/// Length, in bytes, of a BLAKE3 (or SHA-256) digest.
+pub const DIGEST_LEN: usize = 32;
+
 #[derive(Copy, Clone, Debug)]
 pub struct Metadata<'a> {
     /// The ANISE version number. Can be used for partial decoding to determine whether a file is compatible with a library.
@@ -23,6 +29,10 @@ pub struct Metadata<'a> {
     pub originator: &'a str,
     /// Unique resource identifier to the metadata of this file. This is for FAIR compliance.
     pub metadata_uri: &'a str,
+    /// BLAKE3 content digest of the non-metadata bytes of this file, for FAIR integrity
+    /// verification (see [`Metadata::verify_integrity`], which only recomputes BLAKE3).
+    /// Absent (`None`) means the file is unverified.
+    pub content_digest: Option<[u8; DIGEST_LEN]>,
 }
 
 impl Default for Metadata<'_> {
@@ -32,6 +42,45 @@ impl Default for Metadata<'_> {
             creation_date: Epoch::now().unwrap(),
             originator: Default::default(),
             metadata_uri: Default::default(),
+            content_digest: None,
+        }
+    }
+}
+
+impl<'a> Metadata<'a> {
+    /// Recomputes the BLAKE3 digest over `data_bytes` (the non-metadata region of the file) and
+    /// compares it against `self.content_digest`.
+    ///
+    /// # Errors
+    /// Returns `IntegrityError::Unverified` if this metadata has no stored digest, or
+    /// `IntegrityError::DigestMismatch` if the recomputed digest does not match.
+    pub fn verify_integrity(&self, data_bytes: &[u8]) -> Result<(), IntegrityError> {
+        let expected = self.content_digest.ok_or(IntegrityError::Unverified)?;
+        let computed: [u8; DIGEST_LEN] = blake3::hash(data_bytes).into();
+
+        if computed == expected {
+            Ok(())
+        } else {
+            Err(IntegrityError::DigestMismatch)
+        }
+    }
+}
+
+/// Errors that can occur while verifying the integrity of an ANISE file against its stored
+/// content digest.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IntegrityError {
+    /// This file's metadata does not carry a content digest, so it cannot be verified.
+    Unverified,
+    /// The recomputed digest does not match the one stored in the metadata.
+    DigestMismatch,
+}
+
+impl fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unverified => write!(f, "no content digest stored in metadata, cannot verify"),
+            Self::DigestMismatch => write!(f, "content digest does not match stored metadata"),
         }
     }
 }
@@ -42,13 +91,17 @@ impl<'a> Encode for Metadata<'a> {
             + self.creation_date.encoded_len()?
             + Utf8StringRef::new(self.originator)?.encoded_len()?
             + Utf8StringRef::new(self.metadata_uri)?.encoded_len()?
+            + OctetStringRef::new(self.content_digest.as_ref().map_or(&[], |d| d.as_slice()))?
+                .encoded_len()?
     }
 
     fn encode(&self, encoder: &mut dyn Writer) -> der::Result<()> {
         self.anise_version.encode(encoder)?;
         self.creation_date.encode(encoder)?;
         Utf8StringRef::new(self.originator)?.encode(encoder)?;
-        Utf8StringRef::new(self.metadata_uri)?.encode(encoder)
+        Utf8StringRef::new(self.metadata_uri)?.encode(encoder)?;
+        OctetStringRef::new(self.content_digest.as_ref().map_or(&[], |d| d.as_slice()))?
+            .encode(encoder)
     }
 }
 
@@ -59,6 +112,17 @@ impl<'a> Decode<'a> for Metadata<'a> {
             creation_date: decoder.decode()?,
             originator: decoder.decode::<Utf8StringRef<'a>>()?.as_str(),
             metadata_uri: decoder.decode::<Utf8StringRef<'a>>()?.as_str(),
+            // Files written before this field existed have nothing left to decode here: treat
+            // that as an absent (unverified) digest instead of a decode error.
+            content_digest: if decoder.is_finished() {
+                None
+            } else {
+                decoder
+                    .decode::<OctetStringRef<'a>>()?
+                    .as_bytes()
+                    .try_into()
+                    .ok()
+            },
         })
     }
 }
@@ -84,6 +148,120 @@ impl<'a> fmt::Display for Metadata<'a> {
             } else {
                 self.metadata_uri
             }
+        )?;
+        writeln!(
+            f,
+            "Content digest: {}",
+            match self.content_digest {
+                Some(digest) => {
+                    let mut hex = String::with_capacity(DIGEST_LEN * 2);
+                    for byte in digest {
+                        hex.push_str(&format!("{byte:02x}"));
+                    }
+                    hex
+                }
+                None => "(unverified)".to_string(),
+            }
         )
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_metadata(content_digest: Option<[u8; DIGEST_LEN]>) -> Metadata<'static> {
+        Metadata {
+            anise_version: ANISE_VERSION,
+            creation_date: Epoch::from_jde_utc(2_451_545.0), // J2000.0
+            originator: "ANISE test suite",
+            metadata_uri: "https://example.org/metadata.json",
+            content_digest,
+        }
+    }
+
+    #[test]
+    fn verify_integrity_without_digest_is_unverified() {
+        let metadata = sample_metadata(None);
+        assert_eq!(
+            metadata.verify_integrity(b"some file bytes"),
+            Err(IntegrityError::Unverified)
+        );
+    }
+
+    #[test]
+    fn verify_integrity_matches_recomputed_digest() {
+        let data = b"the non-metadata bytes of an ANISE file";
+        let digest: [u8; DIGEST_LEN] = blake3::hash(data).into();
+        let metadata = sample_metadata(Some(digest));
+
+        assert_eq!(metadata.verify_integrity(data), Ok(()));
+    }
+
+    #[test]
+    fn verify_integrity_rejects_tampered_data() {
+        let data = b"the non-metadata bytes of an ANISE file";
+        let digest: [u8; DIGEST_LEN] = blake3::hash(data).into();
+        let metadata = sample_metadata(Some(digest));
+
+        assert_eq!(
+            metadata.verify_integrity(b"tampered bytes"),
+            Err(IntegrityError::DigestMismatch)
+        );
+    }
+
+    #[test]
+    fn encode_decode_round_trip_with_digest() {
+        let digest = [0xABu8; DIGEST_LEN];
+        let metadata = sample_metadata(Some(digest));
+
+        let der_bytes = metadata.to_der().unwrap();
+        let decoded = Metadata::from_der(&der_bytes).unwrap();
+
+        assert_eq!(decoded.originator, metadata.originator);
+        assert_eq!(decoded.metadata_uri, metadata.metadata_uri);
+        assert_eq!(decoded.content_digest, Some(digest));
+    }
+
+    #[test]
+    fn decoding_a_legacy_four_field_encoding_yields_no_digest() {
+        // Simulates a file written before `content_digest` existed: a `Metadata` encoded with
+        // only the first four fields, nothing left in the buffer for the new one.
+        struct LegacyMetadata<'a> {
+            anise_version: Semver,
+            creation_date: Epoch,
+            originator: &'a str,
+            metadata_uri: &'a str,
+        }
+
+        impl<'a> Encode for LegacyMetadata<'a> {
+            fn encoded_len(&self) -> der::Result<der::Length> {
+                self.anise_version.encoded_len()?
+                    + self.creation_date.encoded_len()?
+                    + Utf8StringRef::new(self.originator)?.encoded_len()?
+                    + Utf8StringRef::new(self.metadata_uri)?.encoded_len()?
+            }
+
+            fn encode(&self, encoder: &mut dyn Writer) -> der::Result<()> {
+                self.anise_version.encode(encoder)?;
+                self.creation_date.encode(encoder)?;
+                Utf8StringRef::new(self.originator)?.encode(encoder)?;
+                Utf8StringRef::new(self.metadata_uri)?.encode(encoder)
+            }
+        }
+
+        let legacy = LegacyMetadata {
+            anise_version: ANISE_VERSION,
+            creation_date: Epoch::from_jde_utc(2_451_545.0),
+            originator: "legacy writer",
+            metadata_uri: "https://example.org/legacy.json",
+        };
+
+        let der_bytes = legacy.to_der().unwrap();
+        let decoded = Metadata::from_der(&der_bytes).unwrap();
+
+        assert_eq!(decoded.originator, legacy.originator);
+        assert_eq!(decoded.metadata_uri, legacy.metadata_uri);
+        assert_eq!(decoded.content_digest, None);
+    }
+}